@@ -0,0 +1,236 @@
+//! Mutual peer authentication: a signed `ClientInfo` exchange run on the
+//! first bidi stream immediately after a connection is established, before
+//! any application data flows. Each side proves ownership of the node id it
+//! claims by signing a nonce pair, so accepting the `peervault/sync/1` ALPN
+//! no longer implies trust on its own.
+
+use iroh::{SecretKey, Signature};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Bumped on wire-incompatible changes to the handshake or `ClientInfo`.
+const PROTOCOL_VERSION: u16 = 1;
+/// Identifies this implementation to the peer; purely informational.
+const CLIENT_NAME: &str = "peervault-iroh";
+
+/// Close code used when the peer's signature doesn't verify or claims an
+/// incompatible protocol version.
+const CLOSE_CODE_AUTH_FAILED: u32 = 401;
+/// Close code used when the peer isn't on the configured allowlist.
+const CLOSE_CODE_NOT_ALLOWLISTED: u32 = 403;
+
+/// What each side announces about itself before signing.
+#[derive(Serialize, Deserialize)]
+struct ClientInfo {
+    protocol_version: u16,
+    client_name: String,
+    nonce: [u8; 32],
+    node_id: String,
+}
+
+/// The verified identity of a peer, exposed to JS via `WasmConnection.peerInfo`.
+#[derive(Clone)]
+pub(crate) struct PeerInfo {
+    pub protocol_version: u16,
+    pub client_name: String,
+    pub node_id: String,
+}
+
+impl PeerInfo {
+    /// Matches the ad hoc JSON shape `getStats` already uses.
+    pub(crate) fn to_json(&self) -> String {
+        format!(
+            r#"{{"protocolVersion": {}, "clientName": {:?}, "nodeId": {:?}}}"#,
+            self.protocol_version, self.client_name, self.node_id
+        )
+    }
+}
+
+/// Runs the handshake over `connection`'s first bidi stream. `is_initiator`
+/// picks who opens vs. accepts that stream (the side calling
+/// `connectWithTicket` opens it). Closes `connection` with a distinct error
+/// code and returns an error on any verification or version failure.
+pub(crate) async fn run(
+    connection: &iroh::endpoint::Connection,
+    secret_key: &SecretKey,
+    own_node_id: &str,
+) -> Result<PeerInfo, JsValue> {
+    let (mut send, mut recv) = if is_initiator(connection) {
+        connection
+            .open_bi()
+            .await
+            .map_err(|e| JsValue::from_str(&format!("Auth stream open failed: {}", e)))?
+    } else {
+        connection
+            .accept_bi()
+            .await
+            .map_err(|e| JsValue::from_str(&format!("Auth stream accept failed: {}", e)))?
+    };
+
+    let own_nonce: [u8; 32] = rand::random();
+    let own_info = ClientInfo {
+        protocol_version: PROTOCOL_VERSION,
+        client_name: CLIENT_NAME.to_string(),
+        nonce: own_nonce,
+        node_id: own_node_id.to_string(),
+    };
+
+    write_frame(&mut send, &serde_json::to_vec(&own_info).unwrap()).await?;
+    let peer_info: ClientInfo = serde_json::from_slice(&read_frame(&mut recv).await?)
+        .map_err(|e| JsValue::from_str(&format!("Malformed ClientInfo: {}", e)))?;
+
+    if peer_info.protocol_version != PROTOCOL_VERSION {
+        connection.close(CLOSE_CODE_AUTH_FAILED.into(), b"protocol version mismatch");
+        return Err(JsValue::from_str(&format!(
+            "Peer protocol version {} is incompatible with {}",
+            peer_info.protocol_version, PROTOCOL_VERSION
+        )));
+    }
+
+    // Sign our own nonce ‖ the peer's nonce, proving we hold the secret key
+    // behind the node id we just announced.
+    let to_sign = [own_nonce.as_slice(), peer_info.nonce.as_slice()].concat();
+    let signature = secret_key.sign(&to_sign);
+    write_frame(&mut send, &signature.to_bytes()).await?;
+
+    let peer_signature_bytes = read_frame(&mut recv).await?;
+    let peer_signature = Signature::from_slice(&peer_signature_bytes)
+        .map_err(|e| JsValue::from_str(&format!("Malformed peer signature: {}", e)))?;
+
+    // Verify against the peer's claimed nonce ‖ our nonce, mirroring the
+    // order they signed in, using the node id that actually carries this
+    // QUIC connection (established by iroh's own transport handshake)
+    // rather than trusting whatever `peer_info.node_id` the peer happened
+    // to put in its ClientInfo frame.
+    let to_verify = [peer_info.nonce.as_slice(), own_nonce.as_slice()].concat();
+    if let Err(reason) = verify_peer(
+        connection.remote_id(),
+        &peer_info.node_id,
+        &to_verify,
+        &peer_signature,
+    ) {
+        connection.close(CLOSE_CODE_AUTH_FAILED.into(), reason.as_bytes());
+        return Err(JsValue::from_str(&format!("Peer verification failed: {}", reason)));
+    }
+
+    Ok(PeerInfo {
+        protocol_version: peer_info.protocol_version,
+        client_name: peer_info.client_name,
+        node_id: peer_info.node_id,
+    })
+}
+
+/// Checks that `claimed_node_id` (self-reported in a `ClientInfo` frame)
+/// matches `authenticated_node_id` (iroh's transport-authenticated
+/// identity for the connection), and that `signature` verifies against
+/// `authenticated_node_id` over `message`. Split out from `run` so this
+/// logic is unit-testable without a live `Connection`.
+fn verify_peer(
+    authenticated_node_id: iroh::EndpointId,
+    claimed_node_id: &str,
+    message: &[u8],
+    signature: &Signature,
+) -> Result<(), &'static str> {
+    if claimed_node_id != authenticated_node_id.to_string() {
+        return Err("declared node id does not match connection");
+    }
+    if authenticated_node_id.verify(message, signature).is_err() {
+        return Err("signature verification failed");
+    }
+    Ok(())
+}
+
+/// Rejects a connection whose remote node id isn't on `allowlist`, closing
+/// it with a distinct error code rather than letting the handshake run.
+pub(crate) fn enforce_allowlist(
+    connection: &iroh::endpoint::Connection,
+    remote_node_id: &str,
+    allowlist: &[String],
+) -> Result<(), JsValue> {
+    if allowlist.iter().any(|id| id == remote_node_id) {
+        return Ok(());
+    }
+    connection.close(CLOSE_CODE_NOT_ALLOWLISTED.into(), b"node id not allowlisted");
+    Err(JsValue::from_str(&format!(
+        "Rejected connection from {}: not on allowlist",
+        remote_node_id
+    )))
+}
+
+/// The side that dialed out (client) opens the handshake stream; the side
+/// that accepted the connection waits for it.
+fn is_initiator(connection: &iroh::endpoint::Connection) -> bool {
+    connection.side().is_client()
+}
+
+async fn write_frame(send: &mut iroh::endpoint::SendStream, bytes: &[u8]) -> Result<(), JsValue> {
+    let len = (bytes.len() as u32).to_be_bytes();
+    send.write_all(&len)
+        .await
+        .map_err(|e| JsValue::from_str(&format!("Auth write length failed: {}", e)))?;
+    send.write_all(bytes)
+        .await
+        .map_err(|e| JsValue::from_str(&format!("Auth write data failed: {}", e)))?;
+    Ok(())
+}
+
+async fn read_frame(recv: &mut iroh::endpoint::RecvStream) -> Result<Vec<u8>, JsValue> {
+    let mut len_buf = [0u8; 4];
+    recv.read_exact(&mut len_buf)
+        .await
+        .map_err(|e| JsValue::from_str(&format!("Auth read length failed: {}", e)))?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len > 4096 {
+        return Err(JsValue::from_str("Auth frame too large"));
+    }
+    let mut data = vec![0u8; len];
+    recv.read_exact(&mut data)
+        .await
+        .map_err(|e| JsValue::from_str(&format!("Auth read data failed: {}", e)))?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_verify(own_nonce: &[u8; 32], peer_nonce: &[u8; 32]) -> Vec<u8> {
+        [peer_nonce.as_slice(), own_nonce.as_slice()].concat()
+    }
+
+    #[test]
+    fn verify_peer_accepts_matching_id_and_signature() {
+        let key = SecretKey::generate(&mut rand::rng());
+        let own_nonce = [1u8; 32];
+        let peer_nonce = [2u8; 32];
+        let message = to_verify(&own_nonce, &peer_nonce);
+        let signature = key.sign(&message);
+
+        assert!(verify_peer(key.id(), &key.id().to_string(), &message, &signature).is_ok());
+    }
+
+    #[test]
+    fn verify_peer_rejects_self_declared_node_id_mismatch() {
+        // The peer signs with `key` but declares someone else's node id in
+        // its ClientInfo frame — this is exactly the attack the transport
+        // identity cross-check exists to catch.
+        let key = SecretKey::generate(&mut rand::rng());
+        let other = SecretKey::generate(&mut rand::rng());
+        let own_nonce = [1u8; 32];
+        let peer_nonce = [2u8; 32];
+        let message = to_verify(&own_nonce, &peer_nonce);
+        let signature = key.sign(&message);
+
+        let result = verify_peer(key.id(), &other.id().to_string(), &message, &signature);
+        assert_eq!(result, Err("declared node id does not match connection"));
+    }
+
+    #[test]
+    fn verify_peer_rejects_bad_signature() {
+        let key = SecretKey::generate(&mut rand::rng());
+        let signature = key.sign(b"a message the verifier never sees");
+
+        let result = verify_peer(key.id(), &key.id().to_string(), b"the actual nonce pair", &signature);
+        assert_eq!(result, Err("signature verification failed"));
+    }
+}
@@ -3,11 +3,17 @@
 //! WASM bindings for Iroh P2P networking in PeerVault.
 //! Exposes Iroh's Endpoint, Connection, and Stream to JavaScript.
 
-use iroh::{Endpoint, EndpointAddr, RelayMap, RelayMode, RelayUrl, SecretKey};
+mod auth;
+mod reconnect;
+mod rpc;
+mod ticket;
+
+use iroh::{Endpoint, RelayMap, RelayMode, RelayUrl, SecretKey};
 use js_sys::{Array, Uint8Array};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
 
 /// Protocol identifier for PeerVault sync
 const PEERVAULT_ALPN: &[u8] = b"peervault/sync/1";
@@ -37,6 +43,10 @@ pub fn init() {
 pub struct WasmEndpoint {
     endpoint: Arc<Endpoint>,
     secret_key: SecretKey,
+    /// Node ids allowed to connect in `acceptConnection`. `None` accepts any
+    /// peer that passes the auth handshake. `Arc`-wrapped so `accept_context`
+    /// can hand a copy to a spawned task without cloning the whole list.
+    allowlist: Option<Arc<Vec<String>>>,
 }
 
 #[wasm_bindgen]
@@ -47,10 +57,15 @@ impl WasmEndpoint {
     /// * `key_bytes` - Optional 32-byte secret key for identity persistence
     /// * `relay_urls` - Optional array of relay server URLs (e.g., ["https://relay.example.com"])
     ///                  If not provided, uses Iroh's default public relays.
+    /// * `allowlist` - Optional array of node id hex strings; `acceptConnection`
+    ///                 rejects any peer not on this list before the auth
+    ///                 handshake runs. An empty array is treated as no
+    ///                 allowlist and accepts every peer.
     #[wasm_bindgen]
     pub async fn create(
         key_bytes: Option<Uint8Array>,
         relay_urls: Option<Array>,
+        allowlist: Option<Array>,
     ) -> Result<WasmEndpoint, JsValue> {
         let secret_key = match key_bytes {
             Some(bytes) => {
@@ -93,9 +108,25 @@ impl WasmEndpoint {
             .await
             .map_err(|e| JsValue::from_str(&format!("Endpoint bind failed: {}", e)))?;
 
+        let allowlist = match allowlist {
+            Some(ids) if ids.length() > 0 => {
+                let mut list = Vec::new();
+                for i in 0..ids.length() {
+                    let id = ids
+                        .get(i)
+                        .as_string()
+                        .ok_or_else(|| JsValue::from_str("Allowlist entry must be a string"))?;
+                    list.push(id);
+                }
+                Some(Arc::new(list))
+            }
+            _ => None,
+        };
+
         Ok(WasmEndpoint {
             endpoint: Arc::new(endpoint),
             secret_key,
+            allowlist,
         })
     }
 
@@ -111,61 +142,125 @@ impl WasmEndpoint {
         Uint8Array::from(self.secret_key.to_bytes().as_slice())
     }
 
-    /// Generate a connection ticket for pairing.
-    /// The ticket contains the node address info needed to connect.
+    /// Generate a compact, signed connection ticket for pairing: a
+    /// base32-encoded, binary-coded `EndpointAddr` signed with this
+    /// endpoint's secret key, optionally expiring after `ttl_seconds`.
     /// This waits for the relay connection to be established.
     #[wasm_bindgen(js_name = generateTicket)]
-    pub async fn generate_ticket(&self) -> Result<String, JsValue> {
+    pub async fn generate_ticket(&self, ttl_seconds: Option<u32>) -> Result<String, JsValue> {
         // Wait for endpoint to be online (connected to relay)
         self.endpoint.online().await;
 
-        // Get endpoint address
         let endpoint_addr = self.endpoint.addr();
+        ticket::generate(&endpoint_addr, &self.secret_key, ttl_seconds)
+    }
 
-        // Serialize to JSON for sharing
-        serde_json::to_string(&endpoint_addr)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize ticket: {}", e)))
+    /// Decode a ticket (either format) without connecting, returning its
+    /// node id / relay / expiry as a JSON string so the UI can preview who
+    /// it's about to pair with.
+    #[wasm_bindgen(js_name = parseTicket)]
+    pub fn parse_ticket(&self, ticket: String) -> Result<String, JsValue> {
+        ticket::preview(&ticket)
     }
 
     /// Connect to a peer using their ticket.
+    ///
+    /// # Arguments
+    /// * `auto_reconnect` - If true, a background supervisor re-dials and
+    ///   re-authenticates the peer when the connection drops and
+    ///   best-effort retransmits any sends that were interrupted (see
+    ///   `onReconnecting`/`onReconnected`/`onFailed`). This does not recover
+    ///   the original `send`/`request` call, which already failed — it only
+    ///   gives the peer a chance to see those bytes once reconnected.
+    /// * `max_retries` - Give up after this many failed reconnect attempts.
+    /// * `give_up_after_ms` - Give up once this many milliseconds have passed
+    ///   since the connection first dropped.
     #[wasm_bindgen(js_name = connectWithTicket)]
-    pub async fn connect_with_ticket(&self, ticket: String) -> Result<WasmConnection, JsValue> {
-        // Parse ticket (JSON) to get EndpointAddr
-        let endpoint_addr: EndpointAddr = serde_json::from_str(&ticket)
-            .map_err(|e| JsValue::from_str(&format!("Invalid ticket: {}", e)))?;
+    pub async fn connect_with_ticket(
+        &self,
+        ticket: String,
+        auto_reconnect: Option<bool>,
+        max_retries: Option<u32>,
+        give_up_after_ms: Option<f64>,
+    ) -> Result<WasmConnection, JsValue> {
+        // Accepts both the compact signed format and legacy bare JSON.
+        let endpoint_addr = ticket::decode_and_check_expiry(&ticket)?.addr;
 
         let remote_endpoint_id = endpoint_addr.id.to_string();
 
-        let connection = self.endpoint
-            .connect(endpoint_addr, PEERVAULT_ALPN)
+        let connection = self
+            .endpoint
+            .connect(endpoint_addr.clone(), PEERVAULT_ALPN)
             .await
             .map_err(|e| JsValue::from_str(&format!("Connection failed: {}", e)))?;
 
+        let peer_info = auth::run(&connection, &self.secret_key, &self.node_id()).await?;
+
+        let slot = Arc::new(Mutex::new(connection));
+
+        let reconnect = if auto_reconnect.unwrap_or(false) {
+            let handle = Arc::new(reconnect::ReconnectHandle::new(
+                self.endpoint.clone(),
+                endpoint_addr,
+                PEERVAULT_ALPN,
+                reconnect::ReconnectConfig {
+                    max_retries,
+                    give_up_after_ms,
+                },
+                self.secret_key.clone(),
+                self.node_id(),
+            ));
+            reconnect::spawn_supervisor(slot.clone(), handle.clone());
+            Some(handle)
+        } else {
+            None
+        };
+
         Ok(WasmConnection {
-            connection,
+            connection: slot,
             remote_node_id: remote_endpoint_id,
+            reconnect,
+            peer_info,
         })
     }
 
     /// Accept an incoming connection.
-    /// This blocks until a connection is received.
+    /// This blocks until a connection is received. Rejects peers outside the
+    /// configured allowlist (if any) and runs the auth handshake before
+    /// returning.
     #[wasm_bindgen(js_name = acceptConnection)]
     pub async fn accept_connection(&self) -> Result<WasmConnection, JsValue> {
-        let incoming = self.endpoint
+        let connection = self.accept_raw().await?;
+        self.accept_context().authenticate(connection).await
+    }
+
+    /// Just the cheap half of `acceptConnection`: waits for the next
+    /// incoming connection without running the (potentially slow) auth
+    /// handshake. Split out so `serve`'s accept loop can hand the handshake
+    /// off to a spawned task per connection instead of running it inline —
+    /// otherwise one peer that never completes it would wedge the whole
+    /// accept loop for every other peer too.
+    pub(crate) async fn accept_raw(&self) -> Result<iroh::endpoint::Connection, JsValue> {
+        let incoming = self
+            .endpoint
             .accept()
             .await
             .ok_or_else(|| JsValue::from_str("Endpoint closed"))?;
 
-        let connection = incoming
+        incoming
             .await
-            .map_err(|e| JsValue::from_str(&format!("Accept failed: {}", e)))?;
-
-        let remote_node_id = connection.remote_id().to_string();
+            .map_err(|e| JsValue::from_str(&format!("Accept failed: {}", e)))
+    }
 
-        Ok(WasmConnection {
-            connection,
-            remote_node_id,
-        })
+    /// A cheaply-cloneable snapshot of what's needed to authenticate an
+    /// already-accepted connection, independent of `&WasmEndpoint`'s borrow
+    /// so it can be moved into a `spawn_local` task.
+    pub(crate) fn accept_context(&self) -> AcceptContext {
+        AcceptContext {
+            secret_key: self.secret_key.clone(),
+            own_node_id: self.node_id(),
+            allowlist: self.allowlist.clone(),
+        }
     }
 
     /// Close the endpoint.
@@ -174,14 +269,62 @@ impl WasmEndpoint {
         self.endpoint.close().await;
         Ok(())
     }
+
+    /// Serve RPC requests: accepts connections and streams forever, calling
+    /// `handler(requestBytes, remoteNodeId)` for each request frame and
+    /// writing back whatever array of response frames it returns (or
+    /// resolves to). Pairs with `WasmConnection.request` on the client side.
+    /// Returns once the endpoint is closed.
+    #[wasm_bindgen]
+    pub async fn serve(&self, handler: js_sys::Function) -> Result<(), JsValue> {
+        rpc::serve(self, handler).await
+    }
+}
+
+/// Enough state to run `acceptConnection`'s allowlist check and auth
+/// handshake on an already-accepted connection, without holding a borrow of
+/// `WasmEndpoint` itself. `rpc::serve` clones one of these once per
+/// `Endpoint` and moves a clone into each connection's spawned task.
+#[derive(Clone)]
+pub(crate) struct AcceptContext {
+    secret_key: SecretKey,
+    own_node_id: String,
+    allowlist: Option<Arc<Vec<String>>>,
+}
+
+impl AcceptContext {
+    pub(crate) async fn authenticate(
+        &self,
+        connection: iroh::endpoint::Connection,
+    ) -> Result<WasmConnection, JsValue> {
+        let remote_node_id = connection.remote_id().to_string();
+
+        if let Some(allowlist) = &self.allowlist {
+            auth::enforce_allowlist(&connection, &remote_node_id, allowlist)?;
+        }
+
+        let peer_info = auth::run(&connection, &self.secret_key, &self.own_node_id).await?;
+
+        Ok(WasmConnection {
+            connection: Arc::new(Mutex::new(connection)),
+            remote_node_id,
+            reconnect: None,
+            peer_info,
+        })
+    }
 }
 
 /// WASM-exposed connection wrapper.
-/// Connection is Clone + Send + Sync, so no Mutex needed.
+///
+/// The live `Connection` sits behind a mutex so a reconnect supervisor can
+/// swap it out in place; `reconnect` is only set when `connectWithTicket`
+/// was called with `autoReconnect: true`.
 #[wasm_bindgen]
 pub struct WasmConnection {
-    connection: iroh::endpoint::Connection,
+    connection: Arc<Mutex<iroh::endpoint::Connection>>,
     remote_node_id: String,
+    reconnect: Option<Arc<reconnect::ReconnectHandle>>,
+    peer_info: auth::PeerInfo,
 }
 
 #[wasm_bindgen]
@@ -193,10 +336,13 @@ impl WasmConnection {
     }
 
     /// Open a new bidirectional stream.
+    ///
+    /// `max_message_len` caps `send`/`receive`/`sendStream`/`receiveStream`
+    /// message sizes on this stream; defaults to 64MB.
     #[wasm_bindgen(js_name = openStream)]
-    pub async fn open_stream(&self) -> Result<WasmStream, JsValue> {
-        // Clone the connection to avoid holding any lock during the async operation
-        let conn = self.connection.clone();
+    pub async fn open_stream(&self, max_message_len: Option<u32>) -> Result<WasmStream, JsValue> {
+        // Clone the connection to avoid holding the lock during the async operation
+        let conn = self.connection.lock().await.clone();
         let (send, recv) = conn
             .open_bi()
             .await
@@ -205,14 +351,19 @@ impl WasmConnection {
         Ok(WasmStream {
             send: Arc::new(Mutex::new(send)),
             recv: Arc::new(Mutex::new(recv)),
+            inflight: self.reconnect.clone(),
+            max_message_len: max_message_len.unwrap_or(DEFAULT_MAX_MESSAGE_LEN),
         })
     }
 
     /// Accept an incoming stream.
+    ///
+    /// `max_message_len` caps `send`/`receive`/`sendStream`/`receiveStream`
+    /// message sizes on this stream; defaults to 64MB.
     #[wasm_bindgen(js_name = acceptStream)]
-    pub async fn accept_stream(&self) -> Result<WasmStream, JsValue> {
-        // Clone the connection to avoid holding any lock during the async operation
-        let conn = self.connection.clone();
+    pub async fn accept_stream(&self, max_message_len: Option<u32>) -> Result<WasmStream, JsValue> {
+        // Clone the connection to avoid holding the lock during the async operation
+        let conn = self.connection.lock().await.clone();
         let (send, recv) = conn
             .accept_bi()
             .await
@@ -221,28 +372,44 @@ impl WasmConnection {
         Ok(WasmStream {
             send: Arc::new(Mutex::new(send)),
             recv: Arc::new(Mutex::new(recv)),
+            inflight: self.reconnect.clone(),
+            max_message_len: max_message_len.unwrap_or(DEFAULT_MAX_MESSAGE_LEN),
         })
     }
 
-    /// Check if connection is still alive.
+    /// Check if the connection is still alive. With `autoReconnect` this
+    /// reflects the supervisor's view (false while a reconnect is in
+    /// progress); otherwise it reflects whether the QUIC connection has
+    /// already been closed.
     #[wasm_bindgen(js_name = isConnected)]
     pub fn is_connected(&self) -> bool {
-        // Simplified check - actual check would require async
-        true
+        if let Some(handle) = &self.reconnect {
+            return handle.connected.load(std::sync::atomic::Ordering::Relaxed);
+        }
+        match self.connection.try_lock() {
+            Ok(conn) => conn.close_reason().is_none(),
+            Err(_) => true,
+        }
     }
 
     /// Get the round-trip time (RTT) in milliseconds.
     /// Returns 0 if not available.
     #[wasm_bindgen(js_name = getRttMs)]
     pub fn get_rtt_ms(&self) -> f64 {
-        self.connection.rtt().as_secs_f64() * 1000.0
+        match self.connection.try_lock() {
+            Ok(conn) => conn.rtt().as_secs_f64() * 1000.0,
+            Err(_) => 0.0,
+        }
     }
 
     /// Get connection statistics as JSON string.
     #[wasm_bindgen(js_name = getStats)]
     pub fn get_stats(&self) -> String {
-        let rtt = self.connection.rtt();
-        let remote_id = self.connection.remote_id().to_string();
+        let Ok(conn) = self.connection.try_lock() else {
+            return r#"{"rttMs": 0, "remoteId": ""}"#.to_string();
+        };
+        let rtt = conn.rtt();
+        let remote_id = conn.remote_id().to_string();
 
         format!(
             r#"{{"rttMs": {}, "remoteId": "{}"}}"#,
@@ -251,69 +418,252 @@ impl WasmConnection {
         )
     }
 
-    /// Close the connection.
+    /// Register a callback fired with the attempt number each time the
+    /// supervisor starts a reconnect attempt. No-op without `autoReconnect`.
+    #[wasm_bindgen(js_name = onReconnecting)]
+    pub fn on_reconnecting(&self, callback: js_sys::Function) {
+        if let Some(handle) = &self.reconnect {
+            *handle.callbacks.on_reconnecting.lock().unwrap() = Some(callback);
+        }
+    }
+
+    /// Register a callback fired once the connection has been restored.
+    /// No-op without `autoReconnect`.
+    #[wasm_bindgen(js_name = onReconnected)]
+    pub fn on_reconnected(&self, callback: js_sys::Function) {
+        if let Some(handle) = &self.reconnect {
+            *handle.callbacks.on_reconnected.lock().unwrap() = Some(callback);
+        }
+    }
+
+    /// Register a callback fired with a reason string once the supervisor
+    /// gives up (`maxRetries`/`giveUpAfterMs` exceeded). No-op without
+    /// `autoReconnect`.
+    #[wasm_bindgen(js_name = onFailed)]
+    pub fn on_failed(&self, callback: js_sys::Function) {
+        if let Some(handle) = &self.reconnect {
+            *handle.callbacks.on_failed.lock().unwrap() = Some(callback);
+        }
+    }
+
+    /// Get the peer's verified identity from the auth handshake, as a JSON
+    /// string (`protocolVersion`, `clientName`, `nodeId`).
+    #[wasm_bindgen(js_name = peerInfo)]
+    pub fn peer_info(&self) -> String {
+        self.peer_info.to_json()
+    }
+
+    /// Close the connection. If `autoReconnect` is on, tells the supervisor
+    /// to treat this as a deliberate shutdown rather than a drop to
+    /// reconnect from — otherwise it would immediately start redialing the
+    /// peer the caller just disconnected from.
     #[wasm_bindgen]
     pub async fn close(&self) -> Result<(), JsValue> {
-        self.connection.close(0u32.into(), b"close");
+        if let Some(handle) = &self.reconnect {
+            handle.shutting_down.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+        self.connection.lock().await.close(0u32.into(), b"close");
         Ok(())
     }
+
+    /// Request/response RPC: opens a stream, sends `request_bytes` as a
+    /// single frame, and reads back exactly `num_expected_responses` frames.
+    /// A response count mismatch is a clean error rather than a hang. Pairs
+    /// with `WasmEndpoint.serve` on the peer handling the request.
+    #[wasm_bindgen]
+    pub async fn request(
+        &self,
+        request_bytes: Uint8Array,
+        num_expected_responses: u32,
+    ) -> Result<Array, JsValue> {
+        let stream = self.open_stream(None).await?;
+        rpc::request(&stream, request_bytes, num_expected_responses).await
+    }
 }
 
+/// Default per-stream message cap, overridable via `openStream`/`acceptStream`.
+const DEFAULT_MAX_MESSAGE_LEN: u32 = 64 * 1024 * 1024;
+/// Chunk size `send` writes in and `receiveStream` reads and delivers in.
+/// `sendStream` writes each chunk at whatever size its provider returns.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
 /// WASM-exposed bidirectional stream.
 #[wasm_bindgen]
 pub struct WasmStream {
     send: Arc<Mutex<iroh::endpoint::SendStream>>,
     recv: Arc<Mutex<iroh::endpoint::RecvStream>>,
+    /// Set when the parent connection has a reconnect supervisor; used to
+    /// record sends as in flight so they can be replayed after a reconnect.
+    inflight: Option<Arc<reconnect::ReconnectHandle>>,
+    max_message_len: u32,
 }
 
 #[wasm_bindgen]
 impl WasmStream {
-    /// Send data on the stream.
-    /// Data is length-prefixed (4 bytes big-endian).
+    /// Send data on the stream in one shot.
+    /// Data is length-prefixed (4 bytes big-endian). Takes the whole message
+    /// up front and writes it through `write_stream` in `STREAM_CHUNK_SIZE`
+    /// chunks; it does not call `sendStream`.
     #[wasm_bindgen]
     pub async fn send(&self, data: Uint8Array) -> Result<(), JsValue> {
         let bytes: Vec<u8> = data.to_vec();
-        let mut send = self.send.lock().await;
-
-        // Write length prefix (4 bytes, big-endian)
-        let len = (bytes.len() as u32).to_be_bytes();
-        send.write_all(&len)
-            .await
-            .map_err(|e| JsValue::from_str(&format!("Write length failed: {}", e)))?;
+        if bytes.len() as u32 > self.max_message_len {
+            return Err(JsValue::from_str("Message exceeds max_message_len"));
+        }
+        let request_id = self
+            .inflight
+            .as_ref()
+            .map(|handle| handle.inflight.begin(bytes.clone()));
+
+        let total_len = bytes.len() as u32;
+        let mut sent = 0usize;
+        self.write_stream(total_len, || {
+            if sent >= bytes.len() {
+                return None;
+            }
+            let end = (sent + STREAM_CHUNK_SIZE).min(bytes.len());
+            let chunk = bytes[sent..end].to_vec();
+            sent = end;
+            Some(chunk)
+        })
+        .await?;
 
-        // Write data
-        send.write_all(&bytes)
-            .await
-            .map_err(|e| JsValue::from_str(&format!("Write data failed: {}", e)))?;
+        // Completed normally: the supervisor no longer needs to replay this.
+        // If the write above errored, the entry stays registered so the next
+        // reconnect reissues it on the new connection.
+        if let (Some(handle), Some(id)) = (&self.inflight, request_id) {
+            handle.inflight.complete(id);
+        }
 
         Ok(())
     }
 
-    /// Receive data from the stream.
-    /// Data is length-prefixed (4 bytes big-endian).
+    /// Receive one full message from the stream.
+    /// Data is length-prefixed (4 bytes big-endian). Checks the prefix the
+    /// same way `receiveStream` does (`read_len_prefix`), then reads the whole
+    /// message into a single buffer; it does not deliver chunks or progress.
     #[wasm_bindgen]
     pub async fn receive(&self) -> Result<Uint8Array, JsValue> {
         let mut recv = self.recv.lock().await;
+        let total_len = read_len_prefix(&mut recv, self.max_message_len).await?;
 
-        // Read length prefix
-        let mut len_buf = [0u8; 4];
-        recv.read_exact(&mut len_buf)
+        let mut data = vec![0u8; total_len as usize];
+        recv.read_exact(&mut data)
             .await
-            .map_err(|e| JsValue::from_str(&format!("Read length failed: {}", e)))?;
-        let len = u32::from_be_bytes(len_buf) as usize;
+            .map_err(|e| JsValue::from_str(&format!("Read data failed: {}", e)))?;
+
+        Ok(Uint8Array::from(data.as_slice()))
+    }
 
-        // Validate length (max 64MB)
-        if len > 64 * 1024 * 1024 {
-            return Err(JsValue::from_str("Message too large"));
+    /// Stream `totalLen` bytes to the peer without buffering them all in
+    /// memory: writes a single length prefix, then repeatedly calls
+    /// `readChunkProvider()` (may return a `Uint8Array`, a `Promise` of one,
+    /// or `null`/`undefined` to signal the end) and writes each chunk in
+    /// turn. Each write awaits the underlying QUIC send window, so a slow
+    /// peer naturally throttles `readChunkProvider` instead of chunks piling
+    /// up unbounded.
+    #[wasm_bindgen(js_name = sendStream)]
+    pub async fn send_stream(
+        &self,
+        read_chunk_provider: js_sys::Function,
+        total_len: u32,
+    ) -> Result<(), JsValue> {
+        if total_len > self.max_message_len {
+            return Err(JsValue::from_str("totalLen exceeds max_message_len"));
         }
 
-        // Read data
-        let mut data = vec![0u8; len];
-        recv.read_exact(&mut data)
+        let mut send = self.send.lock().await;
+        send.write_all(&total_len.to_be_bytes())
             .await
-            .map_err(|e| JsValue::from_str(&format!("Read data failed: {}", e)))?;
+            .map_err(|e| JsValue::from_str(&format!("Write length failed: {}", e)))?;
 
-        Ok(Uint8Array::from(data.as_slice()))
+        let mut sent = 0u32;
+        loop {
+            let result = read_chunk_provider.call0(&JsValue::NULL)?;
+            let result = match result.clone().dyn_into::<js_sys::Promise>() {
+                Ok(promise) => wasm_bindgen_futures::JsFuture::from(promise).await?,
+                Err(_) => result,
+            };
+            if result.is_null() || result.is_undefined() {
+                break;
+            }
+            let chunk: Uint8Array = result.dyn_into().map_err(|_| {
+                JsValue::from_str("readChunkProvider must resolve to a Uint8Array or null")
+            })?;
+            let bytes = chunk.to_vec();
+            sent = sent.saturating_add(bytes.len() as u32);
+            if sent > total_len {
+                return Err(JsValue::from_str("sendStream wrote more bytes than totalLen"));
+            }
+            // Backpressure: write_all awaits until the peer's QUIC flow
+            // control window has room, rather than buffering unboundedly.
+            send.write_all(&bytes)
+                .await
+                .map_err(|e| JsValue::from_str(&format!("Write chunk failed: {}", e)))?;
+        }
+
+        if sent != total_len {
+            return Err(JsValue::from_str(&format!(
+                "sendStream wrote {} bytes, expected {}",
+                sent, total_len
+            )));
+        }
+        Ok(())
+    }
+
+    /// Read a streamed message without buffering it all in memory: reads the
+    /// length prefix, then delivers each chunk to `onChunk(chunk)` as it
+    /// arrives, calling `onProgress(bytesReceived, totalLen)` after each one
+    /// so callers can drive a progress bar.
+    #[wasm_bindgen(js_name = receiveStream)]
+    pub async fn receive_stream(
+        &self,
+        on_chunk: js_sys::Function,
+        on_progress: js_sys::Function,
+    ) -> Result<(), JsValue> {
+        let mut recv = self.recv.lock().await;
+        let total_len = read_len_prefix(&mut recv, self.max_message_len).await?;
+
+        let mut received = 0u32;
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        while received < total_len {
+            let want = (total_len - received).min(STREAM_CHUNK_SIZE as u32) as usize;
+            let n = recv
+                .read(&mut buf[..want])
+                .await
+                .map_err(|e| JsValue::from_str(&format!("Read chunk failed: {}", e)))?
+                .ok_or_else(|| JsValue::from_str("Stream ended before totalLen was reached"))?;
+
+            received += n as u32;
+            let chunk = Uint8Array::from(&buf[..n]);
+            on_chunk.call1(&JsValue::NULL, &chunk)?;
+            on_progress.call2(
+                &JsValue::NULL,
+                &JsValue::from_f64(received as f64),
+                &JsValue::from_f64(total_len as f64),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Shared core for one-shot sends: writes the length prefix then drains
+    /// `next_chunk` until it returns `None`.
+    async fn write_stream(
+        &self,
+        total_len: u32,
+        mut next_chunk: impl FnMut() -> Option<Vec<u8>>,
+    ) -> Result<(), JsValue> {
+        let mut send = self.send.lock().await;
+        send.write_all(&total_len.to_be_bytes())
+            .await
+            .map_err(|e| JsValue::from_str(&format!("Write length failed: {}", e)))?;
+
+        while let Some(chunk) = next_chunk() {
+            send.write_all(&chunk)
+                .await
+                .map_err(|e| JsValue::from_str(&format!("Write chunk failed: {}", e)))?;
+        }
+        Ok(())
     }
 
     /// Close the stream.
@@ -325,3 +675,21 @@ impl WasmStream {
         Ok(())
     }
 }
+
+/// Reads and validates the 4-byte big-endian length prefix shared by
+/// `receive` and `receiveStream`.
+async fn read_len_prefix(
+    recv: &mut iroh::endpoint::RecvStream,
+    max_message_len: u32,
+) -> Result<u32, JsValue> {
+    let mut len_buf = [0u8; 4];
+    recv.read_exact(&mut len_buf)
+        .await
+        .map_err(|e| JsValue::from_str(&format!("Read length failed: {}", e)))?;
+    let len = u32::from_be_bytes(len_buf);
+
+    if len > max_message_len {
+        return Err(JsValue::from_str("Message too large"));
+    }
+    Ok(len)
+}
@@ -0,0 +1,317 @@
+//! Automatic reconnection for `WasmConnection`, modeled on the RRR
+//! (Reconnection & Request Reissuance) pattern: a background supervisor
+//! watches the live `iroh::endpoint::Connection` for closure, re-dials the
+//! peer with exponential backoff (re-running the chunk0-3 auth handshake on
+//! the new connection before it's trusted), and best-effort retransmits any
+//! stream writes that were interrupted mid-flight once the replacement
+//! connection is up.
+//!
+//! This is best-effort, fire-and-forget retransmission, not transparent
+//! request reissuance: by the time a reconnect completes, the original
+//! `send`/`request` call has already failed and its caller has already
+//! observed that error. The retransmitted bytes are written to a fresh
+//! stream with no reader wired up to deliver a response back to that
+//! original caller — they exist so the *peer* still sees the bytes at
+//! least once, not so the original call succeeds. Callers that need a
+//! result must retry themselves after an error.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+
+use iroh::{Endpoint, EndpointAddr, SecretKey};
+use tokio::sync::Mutex;
+use wasm_bindgen::prelude::*;
+
+use crate::auth;
+
+/// Backoff ceiling after the first failed reconnect attempt; the first
+/// attempt itself dials immediately.
+const INITIAL_BACKOFF_MS: u64 = 250;
+/// Backoff is capped here so a long outage doesn't balloon into minutes
+/// between attempts.
+const MAX_BACKOFF_MS: u64 = 30_000;
+
+/// Exponential backoff with full jitter, doubling per attempt up to the cap.
+pub(crate) fn next_backoff_ms(attempt: u32) -> u64 {
+    let ceiling = INITIAL_BACKOFF_MS
+        .saturating_mul(1u64 << attempt.min(20))
+        .min(MAX_BACKOFF_MS);
+    (js_sys::Math::random() * ceiling as f64) as u64
+}
+
+/// Bytes from a send that was in flight when the connection dropped, kept
+/// around so it can be reissued on the new connection after reconnecting.
+#[derive(Clone)]
+pub(crate) struct InFlightRequest {
+    pub bytes: Vec<u8>,
+}
+
+/// Registry of in-flight request bytes keyed by a request id, so a send
+/// interrupted by a connection drop can be replayed once reconnected.
+#[derive(Default)]
+pub(crate) struct InFlightRegistry {
+    next_id: AtomicU64,
+    entries: StdMutex<HashMap<u64, InFlightRequest>>,
+}
+
+impl InFlightRegistry {
+    /// Records `bytes` as in flight and returns the id to `complete` it with.
+    pub(crate) fn begin(&self, bytes: Vec<u8>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(id, InFlightRequest { bytes });
+        id
+    }
+
+    /// Marks a request as finished; it no longer needs replaying.
+    pub(crate) fn complete(&self, id: u64) {
+        self.entries.lock().unwrap().remove(&id);
+    }
+
+    fn drain(&self) -> Vec<InFlightRequest> {
+        self.entries.lock().unwrap().drain().map(|(_, v)| v).collect()
+    }
+}
+
+/// Retry policy for the reconnect supervisor, set from `connectWithTicket`.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct ReconnectConfig {
+    pub max_retries: Option<u32>,
+    pub give_up_after_ms: Option<f64>,
+}
+
+/// The JS callbacks the supervisor fires as it changes state. Each slot is
+/// `None` until the caller registers one via `onReconnecting`/etc.
+#[derive(Default)]
+pub(crate) struct ReconnectCallbacks {
+    pub on_reconnecting: StdMutex<Option<js_sys::Function>>,
+    pub on_reconnected: StdMutex<Option<js_sys::Function>>,
+    pub on_failed: StdMutex<Option<js_sys::Function>>,
+}
+
+impl ReconnectCallbacks {
+    fn fire0(slot: &StdMutex<Option<js_sys::Function>>) {
+        if let Some(f) = slot.lock().unwrap().as_ref() {
+            let _ = f.call0(&JsValue::NULL);
+        }
+    }
+
+    fn fire1(slot: &StdMutex<Option<js_sys::Function>>, arg: JsValue) {
+        if let Some(f) = slot.lock().unwrap().as_ref() {
+            let _ = f.call1(&JsValue::NULL, &arg);
+        }
+    }
+
+    pub(crate) fn fire_reconnecting(&self, attempt: u32) {
+        Self::fire1(&self.on_reconnecting, JsValue::from_f64(attempt as f64));
+    }
+
+    pub(crate) fn fire_reconnected(&self) {
+        Self::fire0(&self.on_reconnected);
+    }
+
+    pub(crate) fn fire_failed(&self, reason: &str) {
+        Self::fire1(&self.on_failed, JsValue::from_str(reason));
+    }
+}
+
+/// Shared state behind a self-healing `WasmConnection`: enough to re-dial
+/// the peer and report status, independent of any single `Connection` value.
+pub(crate) struct ReconnectHandle {
+    pub endpoint: Arc<Endpoint>,
+    pub endpoint_addr: EndpointAddr,
+    pub alpn: &'static [u8],
+    pub connected: AtomicBool,
+    pub callbacks: ReconnectCallbacks,
+    pub inflight: InFlightRegistry,
+    pub config: ReconnectConfig,
+    /// Set by `WasmConnection::close` before it closes the connection, so
+    /// the supervisor can tell a deliberate close from a network drop and
+    /// not redial a peer the caller just disconnected from.
+    pub shutting_down: AtomicBool,
+    secret_key: SecretKey,
+    own_node_id: String,
+    attempt: AtomicU32,
+}
+
+impl ReconnectHandle {
+    pub(crate) fn new(
+        endpoint: Arc<Endpoint>,
+        endpoint_addr: EndpointAddr,
+        alpn: &'static [u8],
+        config: ReconnectConfig,
+        secret_key: SecretKey,
+        own_node_id: String,
+    ) -> Self {
+        Self {
+            endpoint,
+            endpoint_addr,
+            alpn,
+            connected: AtomicBool::new(true),
+            callbacks: ReconnectCallbacks::default(),
+            inflight: InFlightRegistry::default(),
+            config,
+            shutting_down: AtomicBool::new(false),
+            secret_key,
+            own_node_id,
+            attempt: AtomicU32::new(0),
+        }
+    }
+}
+
+/// Spawns the task that watches `slot` for closure, re-dials and
+/// re-authenticates the peer, and swaps in the resulting `Connection`,
+/// best-effort retransmitting any requests left in `handle.inflight` (see
+/// the module docs for why that's not the same as the caller seeing a
+/// result). Runs until `giveUpAfter`/`maxRetries` is exceeded or
+/// `handle.shutting_down` is set by `WasmConnection::close`, at which point
+/// it fires `onFailed` (unless shutting down) and exits; the caller must
+/// treat the connection as dead.
+pub(crate) fn spawn_supervisor(
+    slot: Arc<Mutex<iroh::endpoint::Connection>>,
+    handle: Arc<ReconnectHandle>,
+) {
+    wasm_bindgen_futures::spawn_local(async move {
+        loop {
+            let dead = slot.lock().await.clone();
+            let reason = dead.closed().await;
+            handle.connected.store(false, Ordering::Relaxed);
+
+            if handle.shutting_down.load(Ordering::Relaxed) {
+                tracing::debug!(peer = %handle.endpoint_addr.id, "connection closed deliberately, not reconnecting");
+                return;
+            }
+
+            tracing::warn!(peer = %handle.endpoint_addr.id, %reason, "connection dropped, reconnecting");
+
+            let deadline = handle
+                .config
+                .give_up_after_ms
+                .map(|ms| js_sys::Date::now() + ms);
+
+            let new_conn = loop {
+                let attempt = handle.attempt.load(Ordering::Relaxed);
+                if let Some(max) = handle.config.max_retries {
+                    if attempt >= max {
+                        handle.callbacks.fire_failed("max retries exceeded");
+                        return;
+                    }
+                }
+                if deadline.is_some_and(|d| js_sys::Date::now() >= d) {
+                    handle.callbacks.fire_failed("give-up timeout exceeded");
+                    return;
+                }
+
+                handle.callbacks.fire_reconnecting(attempt);
+
+                let dial_result = handle
+                    .endpoint
+                    .connect(handle.endpoint_addr.clone(), handle.alpn)
+                    .await;
+
+                // The fresh connection is only trustworthy once it's passed
+                // the same chunk0-3 auth handshake the first connection did
+                // — otherwise a server-side accept loop waiting on a
+                // ClientInfo frame this client never sends would hang.
+                let handshake_result = match dial_result {
+                    Ok(conn) => auth::run(&conn, &handle.secret_key, &handle.own_node_id)
+                        .await
+                        .map(|_peer_info| conn)
+                        .map_err(|e| e.as_string().unwrap_or_default()),
+                    Err(e) => Err(e.to_string()),
+                };
+
+                match handshake_result {
+                    Ok(conn) => break conn,
+                    Err(e) => {
+                        tracing::warn!(attempt, error = %e, "reconnect attempt failed");
+                        let backoff = next_backoff_ms(attempt);
+                        handle.attempt.fetch_add(1, Ordering::Relaxed);
+                        gloo_timers::future::TimeoutFuture::new(backoff as u32).await;
+                    }
+                }
+            };
+
+            *slot.lock().await = new_conn.clone();
+            handle.connected.store(true, Ordering::Relaxed);
+            handle.attempt.store(0, Ordering::Relaxed);
+            handle.callbacks.fire_reconnected();
+
+            if let Err(e) = replay_inflight(&new_conn, &handle.inflight).await {
+                tracing::warn!(error = %e, "failed to replay in-flight requests after reconnect");
+            }
+        }
+    });
+}
+
+/// Best-effort re-send of every buffered in-flight request on a fresh bidi
+/// stream. Nothing reads a reply on this stream and nothing links it back
+/// to the original caller's (already-failed) `send`/`request` promise —
+/// this exists purely so the peer observes the bytes at least once, not to
+/// resolve the original call.
+async fn replay_inflight(
+    conn: &iroh::endpoint::Connection,
+    registry: &InFlightRegistry,
+) -> Result<(), String> {
+    for req in registry.drain() {
+        let (mut send, _recv) = conn.open_bi().await.map_err(|e| e.to_string())?;
+        let len = (req.bytes.len() as u32).to_be_bytes();
+        send.write_all(&len).await.map_err(|e| e.to_string())?;
+        send.write_all(&req.bytes).await.map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `next_backoff_ms` calls `js_sys::Math::random`, so it needs a JS host
+    // to run under rather than a plain `#[test]`.
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn next_backoff_ms_is_bounded_by_the_exponential_ceiling() {
+        for attempt in 0..8 {
+            let ceiling = INITIAL_BACKOFF_MS.saturating_mul(1u64 << attempt).min(MAX_BACKOFF_MS);
+            let backoff = next_backoff_ms(attempt);
+            assert!(backoff <= ceiling, "attempt {attempt}: {backoff} > {ceiling}");
+        }
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn next_backoff_ms_caps_out_for_large_attempts() {
+        let backoff = next_backoff_ms(1000);
+        assert!(backoff <= MAX_BACKOFF_MS);
+    }
+
+    #[test]
+    fn inflight_registry_begin_then_complete_leaves_nothing_to_drain() {
+        let registry = InFlightRegistry::default();
+        let id = registry.begin(vec![1, 2, 3]);
+        registry.complete(id);
+        assert!(registry.drain().is_empty());
+    }
+
+    #[test]
+    fn inflight_registry_drain_returns_uncompleted_requests() {
+        let registry = InFlightRegistry::default();
+        registry.begin(vec![1, 2, 3]);
+        registry.begin(vec![4, 5]);
+
+        let mut drained: Vec<Vec<u8>> = registry.drain().into_iter().map(|r| r.bytes).collect();
+        drained.sort();
+
+        assert_eq!(drained, vec![vec![1, 2, 3], vec![4, 5]]);
+        assert!(registry.drain().is_empty());
+    }
+
+    #[test]
+    fn inflight_registry_ids_are_unique() {
+        let registry = InFlightRegistry::default();
+        let a = registry.begin(vec![]);
+        let b = registry.begin(vec![]);
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,153 @@
+//! Request/response RPC layer over `WasmStream`'s length-prefixed framing,
+//! mirroring Iroh's LocalRequest/RemoteRequest pattern: a caller writes one
+//! request frame and reads back a fixed number of response frames without
+//! hand-rolling `openStream`/`send`/`receive` in JavaScript.
+
+use futures_util::future::{select, Either};
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::{WasmEndpoint, WasmStream};
+
+/// How long the overrun check below waits for an unexpected extra frame
+/// before giving up on ever seeing one.
+const OVERRUN_CHECK_TIMEOUT_MS: u32 = 5_000;
+
+/// Writes `request_bytes` as a single length-prefixed frame on `stream`,
+/// reads back exactly `num_expected_responses` length-prefixed frames, and
+/// closes the stream. A response count mismatch is a clean error rather
+/// than a hang.
+pub(crate) async fn request(
+    stream: &WasmStream,
+    request_bytes: Uint8Array,
+    num_expected_responses: u32,
+) -> Result<Array, JsValue> {
+    stream.send(request_bytes).await?;
+
+    let responses = Array::new();
+    for received in 0..num_expected_responses {
+        let frame = stream.receive().await.map_err(|e| {
+            JsValue::from_str(&format!(
+                "RPC response underrun: expected {} responses, got {} before error: {}",
+                num_expected_responses,
+                received,
+                e.as_string().unwrap_or_default(),
+            ))
+        })?;
+        responses.push(&frame);
+    }
+
+    // A peer that writes more frames than expected desyncs whatever reads
+    // this stream next, so catch it here instead of leaving it to surface
+    // as a confusing failure elsewhere. This read is raced against a
+    // timeout rather than awaited outright: a well-behaved peer finishes
+    // its send side right after the last expected frame, which ends this
+    // read promptly, but a peer (or buggy handler) that writes exactly
+    // `num_expected_responses` frames and never finishes would otherwise
+    // hang this call forever waiting for a frame that's never coming.
+    let overrun_check = select(
+        Box::pin(stream.receive()),
+        Box::pin(gloo_timers::future::TimeoutFuture::new(OVERRUN_CHECK_TIMEOUT_MS)),
+    )
+    .await;
+    if let Either::Left((Ok(_), _)) = overrun_check {
+        return Err(JsValue::from_str(&format!(
+            "RPC response overrun: received more than the expected {} responses",
+            num_expected_responses
+        )));
+    }
+
+    stream.close().await?;
+    Ok(responses)
+}
+
+/// Accepts connections and streams forever, calling
+/// `handler(requestBytes, remoteNodeId)` for each request frame received and
+/// writing back whatever array of response frames it returns (or resolves
+/// to, if it's async). Returns once the endpoint is closed.
+///
+/// The accept loop itself only does the cheap raw accept
+/// (`WasmEndpoint::accept_raw`) and immediately spawns the allowlist check +
+/// auth handshake (`AcceptContext::authenticate`) as its own task. A peer
+/// that stalls mid-handshake only blocks its own task, not the loop that
+/// lets every other peer in.
+pub(crate) async fn serve(endpoint: &WasmEndpoint, handler: js_sys::Function) -> Result<(), JsValue> {
+    let ctx = endpoint.accept_context();
+
+    loop {
+        let raw_connection = match endpoint.accept_raw().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                if endpoint.endpoint.is_closed() {
+                    return Ok(());
+                }
+                // A single incoming connection failed; keep serving the rest.
+                tracing::warn!(error = ?e, "incoming connection failed");
+                continue;
+            }
+        };
+        let handler = handler.clone();
+        let ctx = ctx.clone();
+
+        wasm_bindgen_futures::spawn_local(async move {
+            let connection = match ctx.authenticate(raw_connection).await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    tracing::warn!(error = ?e, "connection rejected during auth handshake");
+                    return;
+                }
+            };
+
+            loop {
+                let stream = match connection.accept_stream(None).await {
+                    Ok(stream) => stream,
+                    Err(_) => return, // connection closed
+                };
+                let handler = handler.clone();
+                let remote_node_id = connection.remote_node_id();
+
+                wasm_bindgen_futures::spawn_local(async move {
+                    if let Err(e) = serve_one(&stream, &handler, &remote_node_id).await {
+                        tracing::warn!(error = ?e, "RPC handler failed");
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Reads one request frame, invokes `handler`, and writes back each frame of
+/// its response array.
+async fn serve_one(
+    stream: &WasmStream,
+    handler: &js_sys::Function,
+    remote_node_id: &str,
+) -> Result<(), JsValue> {
+    let request_bytes = stream.receive().await?;
+
+    let result = handler.call2(
+        &JsValue::NULL,
+        &request_bytes,
+        &JsValue::from_str(remote_node_id),
+    )?;
+
+    // Handlers may be async; await a Promise if one comes back.
+    let result = match result.clone().dyn_into::<js_sys::Promise>() {
+        Ok(promise) => wasm_bindgen_futures::JsFuture::from(promise).await?,
+        Err(_) => result,
+    };
+
+    let responses: Array = result
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("RPC handler must return an array of response frames"))?;
+
+    for i in 0..responses.length() {
+        let frame: Uint8Array = responses.get(i).dyn_into().map_err(|_| {
+            JsValue::from_str("RPC handler response array must contain Uint8Array frames")
+        })?;
+        stream.send(frame).await?;
+    }
+
+    stream.close().await
+}
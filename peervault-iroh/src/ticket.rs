@@ -0,0 +1,275 @@
+//! Compact, signed, expiring pairing tickets.
+//!
+//! `generateTicket` used to be a bare `serde_json::to_string(&EndpointAddr)`
+//! — verbose, unauthenticated, and non-expiring. The compact format below
+//! binary-encodes the address, stamps it with an issued-at time and
+//! optional TTL, signs the whole payload with the endpoint's `SecretKey`,
+//! and base32-encodes the result into a short `peervault1...` string.
+//! `connectWithTicket`/`parseTicket` auto-detect and still accept the old
+//! bare-JSON format for tickets minted before this existed.
+
+use iroh::{EndpointAddr, EndpointId, SecretKey, Signature};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Every compact ticket starts with this so it's trivially distinguishable
+/// from a legacy JSON ticket (which starts with `{`).
+const TICKET_PREFIX: &str = "peervault1";
+
+/// What gets signed: the address plus enough metadata to check expiry.
+#[derive(Serialize, Deserialize)]
+struct TicketPayload {
+    addr: EndpointAddr,
+    issued_at: u64,
+    ttl_seconds: Option<u32>,
+}
+
+/// A decoded ticket, legacy or compact, with expiry/signing metadata for
+/// `parseTicket` to report back to the UI.
+pub(crate) struct DecodedTicket {
+    pub addr: EndpointAddr,
+    pub issued_at: u64,
+    pub ttl_seconds: Option<u32>,
+    pub signed: bool,
+}
+
+impl DecodedTicket {
+    /// Whether `ttl_seconds` has elapsed since `issued_at`. Always `false`
+    /// for tickets with no TTL.
+    pub(crate) fn is_expired(&self) -> bool {
+        self.ttl_seconds
+            .is_some_and(|ttl| now_secs() >= self.issued_at.saturating_add(ttl as u64))
+    }
+}
+
+/// Builds a compact signed ticket for `addr`, optionally expiring after
+/// `ttl_seconds`.
+pub(crate) fn generate(
+    addr: &EndpointAddr,
+    secret_key: &SecretKey,
+    ttl_seconds: Option<u32>,
+) -> Result<String, JsValue> {
+    let payload = TicketPayload {
+        addr: addr.clone(),
+        issued_at: now_secs(),
+        ttl_seconds,
+    };
+
+    let payload_bytes = bincode::serialize(&payload)
+        .map_err(|e| JsValue::from_str(&format!("Failed to encode ticket: {}", e)))?;
+    let signature = secret_key.sign(&payload_bytes);
+
+    let mut wire = payload_bytes;
+    wire.extend_from_slice(&signature.to_bytes());
+
+    Ok(format!(
+        "{}{}",
+        TICKET_PREFIX,
+        data_encoding::BASE32_NOPAD.encode(&wire)
+    ))
+}
+
+/// Decodes and, for compact tickets, verifies a ticket of either format.
+/// Does not enforce expiry — `parseTicket`/`preview` need to be able to
+/// describe an expired ticket rather than erroring on it. Use
+/// `decode_and_check_expiry` to additionally reject an expired one, which
+/// is what `connectWithTicket` wants.
+pub(crate) fn decode(ticket: &str) -> Result<DecodedTicket, JsValue> {
+    if let Some(encoded) = ticket.strip_prefix(TICKET_PREFIX) {
+        return decode_compact(encoded);
+    }
+
+    // Legacy format: bare JSON, unsigned and non-expiring.
+    let addr: EndpointAddr = serde_json::from_str(ticket)
+        .map_err(|e| JsValue::from_str(&format!("Invalid ticket: {}", e)))?;
+    Ok(DecodedTicket {
+        addr,
+        issued_at: 0,
+        ttl_seconds: None,
+        signed: false,
+    })
+}
+
+fn decode_compact(encoded: &str) -> Result<DecodedTicket, JsValue> {
+    let wire = data_encoding::BASE32_NOPAD
+        .decode(encoded.as_bytes())
+        .map_err(|e| JsValue::from_str(&format!("Invalid ticket encoding: {}", e)))?;
+
+    if wire.len() < 64 {
+        return Err(JsValue::from_str("Ticket too short to contain a signature"));
+    }
+    let (payload_bytes, signature_bytes) = wire.split_at(wire.len() - 64);
+
+    let payload: TicketPayload = bincode::deserialize(payload_bytes)
+        .map_err(|e| JsValue::from_str(&format!("Malformed ticket payload: {}", e)))?;
+
+    let signature = Signature::from_slice(signature_bytes)
+        .map_err(|e| JsValue::from_str(&format!("Malformed ticket signature: {}", e)))?;
+
+    let issuer: EndpointId = payload.addr.id;
+    if issuer.verify(payload_bytes, &signature).is_err() {
+        return Err(JsValue::from_str("Invalid ticket signature"));
+    }
+
+    Ok(DecodedTicket {
+        addr: payload.addr,
+        issued_at: payload.issued_at,
+        ttl_seconds: payload.ttl_seconds,
+        signed: true,
+    })
+}
+
+/// Decodes a ticket and additionally rejects it if expired. What
+/// `connectWithTicket` uses, since actually pairing with an expired ticket
+/// should fail rather than just be reported as stale.
+pub(crate) fn decode_and_check_expiry(ticket: &str) -> Result<DecodedTicket, JsValue> {
+    let decoded = decode(ticket)?;
+    if decoded.is_expired() {
+        return Err(JsValue::from_str("Ticket expired"));
+    }
+    Ok(decoded)
+}
+
+/// JSON preview of a ticket's contents for `WasmEndpoint.parseTicket`, so a
+/// UI can show who it's about to pair with before connecting. Deliberately
+/// does not reject an expired ticket the way `connectWithTicket` does — the
+/// whole point of previewing is to be able to show the user "this ticket is
+/// expired" via the `expired` field rather than just throwing.
+pub(crate) fn preview(ticket: &str) -> Result<String, JsValue> {
+    let decoded = decode(ticket)?;
+    let expired = decoded.is_expired();
+
+    let mut value = serde_json::to_value(&decoded.addr)
+        .map_err(|e| JsValue::from_str(&format!("Failed to describe ticket: {}", e)))?;
+    if let serde_json::Value::Object(ref mut map) = value {
+        map.insert("issuedAt".to_string(), decoded.issued_at.into());
+        map.insert(
+            "expiresAt".to_string(),
+            match decoded.ttl_seconds {
+                Some(ttl) => (decoded.issued_at.saturating_add(ttl as u64)).into(),
+                None => serde_json::Value::Null,
+            },
+        );
+        map.insert("signed".to_string(), decoded.signed.into());
+        map.insert("expired".to_string(), expired.into());
+    }
+
+    serde_json::to_string(&value)
+        .map_err(|e| JsValue::from_str(&format!("Failed to describe ticket: {}", e)))
+}
+
+fn now_secs() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_addr(secret_key: &SecretKey) -> EndpointAddr {
+        EndpointAddr::new(secret_key.id())
+    }
+
+    // `generate`/`decode` call `js_sys::Date::now`, so these need a JS host
+    // to run under rather than a plain `#[test]`.
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn generate_then_decode_round_trips_and_verifies() {
+        let secret_key = SecretKey::generate(&mut rand::rng());
+        let addr = sample_addr(&secret_key);
+
+        let ticket = generate(&addr, &secret_key, None).unwrap();
+        assert!(ticket.starts_with(TICKET_PREFIX));
+
+        let decoded = decode(&ticket).unwrap();
+        assert_eq!(decoded.addr.id, addr.id);
+        assert!(decoded.signed);
+        assert_eq!(decoded.ttl_seconds, None);
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn decode_rejects_a_tampered_ticket() {
+        let secret_key = SecretKey::generate(&mut rand::rng());
+        let addr = sample_addr(&secret_key);
+        let ticket = generate(&addr, &secret_key, None).unwrap();
+
+        // Swap the first payload character for a different valid base32
+        // character, so the decoded payload no longer matches the signature
+        // it's checked against (or no longer parses at all).
+        let first = ticket.as_bytes()[TICKET_PREFIX.len()];
+        let replacement = if first == b'A' { 'B' } else { 'A' };
+        let mangled = format!(
+            "{}{}{}",
+            TICKET_PREFIX,
+            replacement,
+            &ticket[TICKET_PREFIX.len() + 1..]
+        );
+        assert!(decode(&mangled).is_err());
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn decode_does_not_reject_an_expired_ticket() {
+        // `decode` (what `preview`/`parseTicket` use) must still be able to
+        // describe an expired ticket rather than erroring on it.
+        let secret_key = SecretKey::generate(&mut rand::rng());
+        let addr = sample_addr(&secret_key);
+        let ticket = generate(&addr, &secret_key, Some(0)).unwrap();
+
+        let decoded = decode(&ticket).unwrap();
+        assert!(decoded.is_expired());
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn decode_and_check_expiry_rejects_an_expired_ticket() {
+        let secret_key = SecretKey::generate(&mut rand::rng());
+        let addr = sample_addr(&secret_key);
+        let ticket = generate(&addr, &secret_key, Some(0)).unwrap();
+
+        let err = decode_and_check_expiry(&ticket).unwrap_err();
+        assert_eq!(err.as_string().unwrap(), "Ticket expired");
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn decode_and_check_expiry_accepts_a_ticket_still_within_its_ttl() {
+        let secret_key = SecretKey::generate(&mut rand::rng());
+        let addr = sample_addr(&secret_key);
+        let ticket = generate(&addr, &secret_key, Some(60)).unwrap();
+
+        assert!(decode_and_check_expiry(&ticket).is_ok());
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn decode_accepts_legacy_bare_json_tickets() {
+        let secret_key = SecretKey::generate(&mut rand::rng());
+        let addr = sample_addr(&secret_key);
+        let legacy = serde_json::to_string(&addr).unwrap();
+
+        let decoded = decode(&legacy).unwrap();
+        assert_eq!(decoded.addr.id, addr.id);
+        assert!(!decoded.signed);
+        assert_eq!(decoded.ttl_seconds, None);
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn preview_reports_expiry_for_a_ticket_with_a_ttl() {
+        let secret_key = SecretKey::generate(&mut rand::rng());
+        let addr = sample_addr(&secret_key);
+        let ticket = generate(&addr, &secret_key, Some(60)).unwrap();
+
+        let json = preview(&ticket).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["signed"], true);
+        assert!(value["expiresAt"].is_number());
+        assert_eq!(value["expired"], false);
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn preview_reports_an_already_expired_ticket_instead_of_erroring() {
+        let secret_key = SecretKey::generate(&mut rand::rng());
+        let addr = sample_addr(&secret_key);
+        let ticket = generate(&addr, &secret_key, Some(0)).unwrap();
+
+        let json = preview(&ticket).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["expired"], true);
+    }
+}